@@ -0,0 +1,2 @@
+pub mod road_dijkstras;
+pub mod road_network;
@@ -0,0 +1,234 @@
+#[allow(unused)]
+#[allow(clippy::module_inception)]
+pub mod road_dijkstras {
+    //dijkstra and a* shortest-path search over a preprocessed RoadNetwork
+    use crate::road_network::road_network::{Node, RoadNetwork};
+    use std::cmp::Ordering;
+    use std::collections::{BinaryHeap, HashMap};
+
+    const MAX_SPEED_KMH: f64 = 110.0; //fastest edge speed in the network, see speed_calc; keeps the a* heuristic admissible
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    struct SearchNode {
+        //binary heap entry, ordered smallest-priority-first
+        priority: u64,
+        node_id: i64,
+    }
+
+    impl Ord for SearchNode {
+        fn cmp(&self, other: &Self) -> Ordering {
+            other
+                .priority
+                .cmp(&self.priority)
+                .then_with(|| self.node_id.cmp(&other.node_id))
+        }
+    }
+
+    impl PartialOrd for SearchNode {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct RoadDijkstra<'a> {
+        //reusable search state over a borrowed RoadNetwork; each search clears the previous query's state
+        graph: &'a RoadNetwork,
+        pub visited_nodes: HashMap<i64, u64>, //node.id, cost at settling time
+        pub distances: HashMap<i64, u64>,      //node.id, best known distance from source
+        pub predecessors: HashMap<i64, i64>,   //node.id, predecessor on shortest path
+    }
+
+    impl<'a> RoadDijkstra<'a> {
+        pub fn new(graph: &'a RoadNetwork) -> Self {
+            Self {
+                graph,
+                visited_nodes: HashMap::new(),
+                distances: HashMap::new(),
+                predecessors: HashMap::new(),
+            }
+        }
+
+        fn reconstruct_path(&self, target_id: i64) -> Vec<i64> {
+            let mut path = vec![target_id];
+            let mut current = target_id;
+            while let Some(&pred) = self.predecessors.get(&current) {
+                path.push(pred);
+                current = pred;
+            }
+            path.reverse();
+            path
+        }
+
+        //shared binary-heap search loop; target_id of -1 means "visit every reachable node" and never returns early
+        fn search(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+            cost_upper_bound: &Option<u64>,
+            heuristic_target: Option<Node>,
+            target_cell: Option<usize>,
+        ) -> Option<(Vec<i64>, u64)> {
+            self.visited_nodes.clear();
+            self.distances.clear();
+            self.predecessors.clear();
+
+            let h = |graph: &RoadNetwork, node_id: i64| -> u64 {
+                match heuristic_target {
+                    Some(target) => graph
+                        .nodes
+                        .get(&node_id)
+                        .map(|node| heuristic_cost(node, &target))
+                        .unwrap_or(0),
+                    None => 0,
+                }
+            };
+
+            let mut heap = BinaryHeap::new();
+            self.distances.insert(source_id, 0);
+            heap.push(SearchNode {
+                priority: h(self.graph, source_id),
+                node_id: source_id,
+            });
+
+            while let Some(SearchNode { node_id, .. }) = heap.pop() {
+                if self.visited_nodes.contains_key(&node_id) {
+                    continue;
+                }
+                let node_cost = *self.distances.get(&node_id).unwrap();
+                self.visited_nodes.insert(node_id, node_cost);
+
+                if node_id == target_id {
+                    return Some((self.reconstruct_path(target_id), node_cost));
+                }
+
+                //flags only mark edges on a shortest path *toward* the target cell's boundary;
+                //once we're already inside that cell the flag check must be skipped, or the
+                //search starves right at the cell's edge instead of reaching an interior target
+                let already_in_target_cell = target_cell.is_some()
+                    && self.graph.partition.get(&node_id).copied() == target_cell;
+
+                let Some(neighbors) = self.graph.edges.get(&node_id) else {
+                    continue;
+                };
+                for (head_id, (edge_cost, arc_flags)) in neighbors {
+                    let head_id = *head_id;
+                    let edge_cost = *edge_cost;
+                    if self.visited_nodes.contains_key(&head_id) {
+                        continue;
+                    }
+                    if let Some(cell_id) = target_cell {
+                        if !already_in_target_cell && !arc_flags.get(cell_id).copied().unwrap_or(false) {
+                            continue;
+                        }
+                    }
+                    let tentative = node_cost + edge_cost;
+                    if let Some(bound) = cost_upper_bound {
+                        if tentative > *bound {
+                            continue;
+                        }
+                    }
+                    let is_better = match self.distances.get(&head_id) {
+                        Some(&best) => tentative < best,
+                        None => true,
+                    };
+                    if is_better {
+                        self.distances.insert(head_id, tentative);
+                        self.predecessors.insert(head_id, node_id);
+                        heap.push(SearchNode {
+                            priority: tentative + h(self.graph, head_id),
+                            node_id: head_id,
+                        });
+                    }
+                }
+            }
+
+            None
+        }
+
+        pub fn dijkstra(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+            cost_upper_bound: &Option<u64>,
+            use_arc_flags: bool,
+        ) -> Option<(Vec<i64>, u64)> {
+            //uninformed search: priority is plain g(n); with use_arc_flags, only relaxes edges
+            //flagged as lying on a shortest path into the target's cell (see compute_arc_flags)
+            let target_cell = match use_arc_flags {
+                true => self.graph.partition.get(&target_id).copied(),
+                false => None,
+            };
+            self.search(source_id, target_id, cost_upper_bound, None, target_cell)
+        }
+
+        pub fn astar(
+            &mut self,
+            source_id: i64,
+            target_id: i64,
+            cost_upper_bound: &Option<u64>,
+        ) -> Option<(Vec<i64>, u64)> {
+            //informed search: priority is g(n) + h(n); h is admissible so the result matches dijkstra's
+            let target_node = *self.graph.nodes.get(&target_id)?;
+            self.search(
+                source_id,
+                target_id,
+                cost_upper_bound,
+                Some(target_node),
+                None,
+            )
+        }
+    }
+
+    fn heuristic_cost(from: &Node, to: &Node) -> u64 {
+        //admissible lower bound: straight-line travel time at the network's fastest possible speed
+        let a = i128::pow(((to.lat - from.lat) * 111229).into(), 2) as f64 / f64::powi(10.0, 14);
+        let b = i128::pow(((to.lon - from.lon) * 71695).into(), 2) as f64 / f64::powi(10.0, 14);
+        let meters = (a + b).sqrt();
+        (meters / (MAX_SPEED_KMH * 5.0 / 18.0)) as u64
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::collections::HashMap;
+
+        fn node(id: i64) -> Node {
+            //coincident coordinates keep heuristic_cost at 0 everywhere, so astar is trivially
+            //admissible and must settle on the exact same path/cost as plain dijkstra
+            Node { id, lat: 0, lon: 0 }
+        }
+
+        fn diamond_graph() -> RoadNetwork {
+            //1 -> 2 direct is expensive; 1 -> 3 -> 4 -> 2 is cheaper, then 2 -> 5
+            let nodes = [1, 2, 3, 4, 5].into_iter().map(|id| (id, node(id))).collect();
+            let mut edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> = HashMap::new();
+            for &(tail, head, cost) in &[(1, 2, 10), (1, 3, 1), (3, 4, 1), (4, 2, 1), (2, 5, 1)] {
+                edges.entry(tail).or_default().insert(head, (cost, Vec::new()));
+            }
+            RoadNetwork::for_testing(nodes, edges)
+        }
+
+        #[test]
+        fn astar_matches_dijkstra_on_shortest_path() {
+            let graph = diamond_graph();
+            let (dijkstra_path, dijkstra_cost) = RoadDijkstra::new(&graph)
+                .dijkstra(1, 5, &None, false)
+                .expect("5 is reachable from 1");
+            let (astar_path, astar_cost) = RoadDijkstra::new(&graph)
+                .astar(1, 5, &None)
+                .expect("5 is reachable from 1");
+
+            assert_eq!(astar_cost, dijkstra_cost);
+            assert_eq!(astar_path, dijkstra_path);
+            assert_eq!(dijkstra_cost, 4); //1 -> 3 -> 4 -> 2 -> 5, not the direct 10-cost 1 -> 2 edge
+            assert_eq!(dijkstra_path, vec![1, 3, 4, 2, 5]);
+        }
+
+        #[test]
+        fn astar_returns_none_when_unreachable() {
+            let graph = diamond_graph();
+            assert!(RoadDijkstra::new(&graph).astar(5, 1, &None).is_none());
+        }
+    }
+}
@@ -1,12 +1,49 @@
 #[allow(unused)]
+#[allow(clippy::module_inception)]
 pub mod road_network {
     //constructs and preprocesses the graph struct from OSM data
-    use crate::road_dijkstras::*;
+    use crate::road_dijkstras::road_dijkstras::RoadDijkstra;
     use core::num;
     use osmpbfreader::objects::OsmObj;
-    use std::{collections::HashMap, ops::Index};
+    use rstar::{PointDistance, RTree, RTreeObject, AABB};
+    use serde::{Deserialize, Serialize};
+    use sha3::{Digest, Sha3_256};
+    use std::{
+        cmp::Reverse,
+        collections::{BinaryHeap, HashMap, HashSet},
+        ops::Index,
+        sync::OnceLock,
+    };
 
-    #[derive(Debug, PartialEq, Hash, Eq, Clone, Copy, PartialOrd, Ord)]
+    #[derive(Debug, Clone, Copy)]
+    struct IndexedNode {
+        //lightweight point wrapper so Node itself doesn't need to know about rstar
+        id: i64,
+        lat: i64,
+        lon: i64,
+    }
+
+    impl RTreeObject for IndexedNode {
+        type Envelope = AABB<[i64; 2]>;
+
+        fn envelope(&self) -> Self::Envelope {
+            AABB::from_point([self.lat, self.lon])
+        }
+    }
+
+    impl PointDistance for IndexedNode {
+        fn distance_2(&self, point: &[i64; 2]) -> i64 {
+            //same lat/lon -> meters scaling used everywhere else in this file (RoadNetwork::new's
+            //edge cost, the a* heuristic) so the index agrees with the rest of the router on
+            //what's actually nearest; a degree of longitude is worth fewer meters than a degree
+            //of latitude, so leaving this unscaled picks a geometrically different node off-equator
+            let dlat = (self.lat - point[0]) as i128 * 111229;
+            let dlon = (self.lon - point[1]) as i128 * 71695;
+            ((dlat * dlat + dlon * dlon) / 10_i128.pow(14)) as i64
+        }
+    }
+
+    #[derive(Debug, PartialEq, Hash, Eq, Clone, Copy, PartialOrd, Ord, Serialize, Deserialize)]
     pub struct Node {
         //nodes from OSM, each with unique ID and coordinate position
         pub id: i64,
@@ -14,24 +51,132 @@ pub mod road_network {
         pub lon: i64,
     }
 
-    #[derive(Debug, PartialEq, Hash, Eq, Clone)]
+    #[derive(Debug, PartialEq, Hash, Eq, Clone, Serialize, Deserialize)]
     pub struct Way {
         //ways from OSM, each with unique ID, speed from highway type, and referenced nodes that it connects
         pub id: i64,
-        pub speed: u64,
+        pub speed: u64, //effective speed: the tagged maxspeed if present, otherwise speed_calc's default
         pub refs: Vec<i64>,
+        pub oneway: bool, //true if edges should only be added tail -> head (refs already face the travel direction)
+        pub maxspeed: Option<u64>, //explicit maxspeed tag, kept around for callers that want the raw value
+    }
+
+    fn parse_maxspeed(raw: &str) -> Option<u64> {
+        //OSM maxspeed values look like "50", "30 mph", "RU:urban", or "none"; only numeric (optionally mph) forms resolve
+        let raw = raw.trim();
+        if raw.eq_ignore_ascii_case("none") {
+            return None;
+        }
+        //some extracts tag maxspeed=0 by mistake; treat it like "none" rather than feeding a
+        //zero divisor into RoadNetwork::new's cost calculation
+        let resolved = if let Some(mph) = raw.strip_suffix("mph").map(str::trim) {
+            mph.parse::<f64>().ok().map(|speed| (speed * 1.60934) as u64)
+        } else {
+            raw.split_whitespace().next()?.parse::<u64>().ok()
+        };
+        resolved.filter(|&speed| speed > 0)
     }
 
-    #[derive(Debug, PartialEq, Clone)]
+    fn parse_oneway(raw: &str) -> (bool, bool) {
+        //(is_oneway, is_reversed); oneway=-1 means the way runs against its node order
+        match raw {
+            "yes" | "true" | "1" => (true, false),
+            "-1" => (true, true),
+            _ => (false, false),
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
     pub struct RoadNetwork {
         //graph struct that will be used to route
         pub nodes: HashMap<i64, Node>, // <node.id, node>
-        pub edges: HashMap<i64, HashMap<i64, (u64, bool)>>, // tail.id, <head.id, (cost, arcflag)>
+        pub edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>>, // tail.id, <head.id, (cost, arcflags by cell id)>
         pub raw_ways: Vec<Way>,
         pub raw_nodes: Vec<i64>,
+        pub partition: HashMap<i64, usize>, // node.id, cell id assigned by compute_arc_flags
+        pub num_cells: usize,
+        #[serde(skip)]
+        spatial_index_cache: OnceLock<RTree<IndexedNode>>, //built once, lazily, from nodes (see spatial_index)
+    }
+
+    impl std::fmt::Debug for RoadNetwork {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RoadNetwork")
+                .field("nodes", &self.nodes)
+                .field("edges", &self.edges)
+                .field("raw_ways", &self.raw_ways)
+                .field("raw_nodes", &self.raw_nodes)
+                .field("partition", &self.partition)
+                .field("num_cells", &self.num_cells)
+                .finish()
+        }
+    }
+
+    impl Clone for RoadNetwork {
+        fn clone(&self) -> Self {
+            //the cached index is rebuilt lazily on next use rather than cloned
+            Self {
+                nodes: self.nodes.clone(),
+                edges: self.edges.clone(),
+                raw_ways: self.raw_ways.clone(),
+                raw_nodes: self.raw_nodes.clone(),
+                partition: self.partition.clone(),
+                num_cells: self.num_cells,
+                spatial_index_cache: OnceLock::new(),
+            }
+        }
+    }
+
+    impl PartialEq for RoadNetwork {
+        fn eq(&self, other: &Self) -> bool {
+            self.nodes == other.nodes
+                && self.edges == other.edges
+                && self.raw_ways == other.raw_ways
+                && self.raw_nodes == other.raw_nodes
+                && self.partition == other.partition
+                && self.num_cells == other.num_cells
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct CachedRoadNetwork {
+        //header + payload written by RoadNetwork::save; source_hash lets load() detect a stale cache
+        source_hash: [u8; 32],
+        network: RoadNetwork,
+    }
+
+    fn hash_file(path: &str) -> std::io::Result<[u8; 32]> {
+        //sha3-256 of the raw .pbf bytes, used to tell a fresh cache from a stale one
+        let bytes = std::fs::read(path)?;
+        let mut hasher = Sha3_256::new();
+        hasher.update(&bytes);
+        Ok(hasher.finalize().into())
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum Profile {
+        //which kind of traveler the graph is being built for; controls both speed and access
+        Car,
+        Bicycle,
+        Pedestrian,
+    }
+
+    impl Profile {
+        fn speed_calc(self, highway: &str) -> Option<u64> {
+            match self {
+                Profile::Car => car_speed_calc(highway),
+                Profile::Bicycle => bicycle_speed_calc(highway),
+                Profile::Pedestrian => pedestrian_speed_calc(highway),
+            }
+        }
     }
 
     pub fn speed_calc(highway: &str) -> Option<u64> {
+        //kept as the car profile's table for existing callers
+        car_speed_calc(highway)
+    }
+
+    fn car_speed_calc(highway: &str) -> Option<u64> {
         //calculates speed of highway based on given values
         match highway {
             "motorway" => Some(110),
@@ -53,10 +198,33 @@ pub mod road_network {
         }
     }
 
+    fn bicycle_speed_calc(highway: &str) -> Option<u64> {
+        //cars' motorway/trunk network is off-limits; footways are usable but slow to share with pedestrians
+        match highway {
+            "cycleway" => Some(18),
+            "primary" | "secondary" | "tertiary" | "residential" | "unclassified" | "road" => {
+                Some(15)
+            }
+            "living_street" | "service" => Some(12),
+            "path" | "track" => Some(12),
+            "footway" | "pedestrian" | "steps" => Some(5),
+            _ => None,
+        }
+    }
+
+    fn pedestrian_speed_calc(highway: &str) -> Option<u64> {
+        //only low-speed and foot-accessible highway types; no motorway/trunk/primary etc.
+        match highway {
+            "footway" | "path" | "pedestrian" | "steps" | "track" => Some(5),
+            "living_street" | "residential" | "service" | "unclassified" => Some(5),
+            _ => None,
+        }
+    }
+
     impl RoadNetwork {
         pub fn new(mut nodes: HashMap<i64, Node>, ways: Vec<Way>) -> Self {
             //init new RoadNetwork based on results from reading .pbf file
-            let mut edges: HashMap<i64, HashMap<i64, (u64, bool)>> = HashMap::new();
+            let mut edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> = HashMap::new();
             for way in ways.clone() {
                 let mut previous_head_node_now_tail: Option<&Node> = None;
                 let mut previous_head_node_index: usize = 0;
@@ -80,27 +248,29 @@ pub mod road_network {
                             / f64::powi(10.0, 14);
                         let c = (a + b).sqrt();
                         let cost = (c as u64) / ((way.speed as f64) * 5.0 / 18.0) as u64; //seconds to traverse segment based on road type
-                        let flag = false;
+                        let flags: Vec<bool> = Vec::new(); //filled in by compute_arc_flags once the graph is partitioned
                         edges
                             .entry(tail_id)
                             .and_modify(|inner| {
-                                inner.insert(head_id, (cost, flag));
+                                inner.insert(head_id, (cost, flags.clone()));
                             })
                             .or_insert({
                                 let mut a = HashMap::new();
-                                a.insert(head_id, (cost, flag));
-                                a
-                            });
-                        edges
-                            .entry(head.id)
-                            .and_modify(|inner| {
-                                inner.insert(tail_id, (cost, flag));
-                            })
-                            .or_insert({
-                                let mut a = HashMap::new();
-                                a.insert(tail_id, (cost, flag));
+                                a.insert(head_id, (cost, flags.clone()));
                                 a
                             });
+                        if !way.oneway {
+                            edges
+                                .entry(head.id)
+                                .and_modify(|inner| {
+                                    inner.insert(tail_id, (cost, flags.clone()));
+                                })
+                                .or_insert({
+                                    let mut a = HashMap::new();
+                                    a.insert(tail_id, (cost, flags.clone()));
+                                    a
+                                });
+                        }
                         previous_head_node_now_tail = Some(head);
                         previous_head_node_index = i + 1;
                     }
@@ -120,11 +290,35 @@ pub mod road_network {
                 edges,
                 raw_nodes: nodes.clone().iter().map(|(&id, _)| id).collect(),
                 nodes,
+                partition: HashMap::new(),
+                num_cells: 0,
+                spatial_index_cache: OnceLock::new(),
+            }
+        }
+
+        //lets test fixtures in this crate build a RoadNetwork straight from a nodes/edges map,
+        //bypassing the OSM-derived raw_ways reconstruction that ::new does
+        #[cfg(test)]
+        pub(crate) fn for_testing(
+            nodes: HashMap<i64, Node>,
+            edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>>,
+        ) -> Self {
+            Self {
+                nodes,
+                edges,
+                raw_ways: Vec::new(),
+                raw_nodes: Vec::new(),
+                partition: HashMap::new(),
+                num_cells: 0,
+                spatial_index_cache: OnceLock::new(),
             }
         }
 
-        pub fn read_from_osm_file(path: &str) -> Option<(HashMap<i64, Node>, Vec<Way>)> {
-            //reads osm.pbf file, values are used to make RoadNetwork
+        pub fn read_from_osm_file(
+            path: &str,
+            profile: Profile,
+        ) -> Option<(HashMap<i64, Node>, Vec<Way>)> {
+            //reads osm.pbf file, keeping only ways the given profile can use, with that profile's speeds
             let mut nodes = HashMap::new();
             let mut ways = Vec::new();
             let path_cleaned = std::path::Path::new(&path);
@@ -146,11 +340,33 @@ pub mod road_network {
                         if let Some(road_type) =
                             e.tags.clone().iter().find(|(k, _)| k.eq(&"highway"))
                         {
-                            if let Some(speed) = speed_calc(road_type.1.as_str()) {
+                            if let Some(default_speed) = profile.speed_calc(road_type.1.as_str()) {
+                                //maxspeed is a vehicle speed limit; only the car profile honors it
+                                let maxspeed = match profile {
+                                    Profile::Car => e
+                                        .tags
+                                        .iter()
+                                        .find(|(k, _)| k.eq(&"maxspeed"))
+                                        .and_then(|(_, v)| parse_maxspeed(v.as_str())),
+                                    Profile::Bicycle | Profile::Pedestrian => None,
+                                };
+                                let (oneway, reversed) = e
+                                    .tags
+                                    .iter()
+                                    .find(|(k, _)| k.eq(&"oneway"))
+                                    .map(|(_, v)| parse_oneway(v.as_str()))
+                                    .unwrap_or((false, false));
+                                let mut refs: Vec<i64> =
+                                    e.nodes.into_iter().map(|x| x.0).collect();
+                                if reversed {
+                                    refs.reverse();
+                                }
                                 ways.push(Way {
                                     id: e.id.0,
-                                    speed,
-                                    refs: e.nodes.into_iter().map(|x| x.0).collect(),
+                                    speed: maxspeed.unwrap_or(default_speed),
+                                    refs,
+                                    oneway,
+                                    maxspeed,
                                 });
                             }
                         }
@@ -161,49 +377,600 @@ pub mod road_network {
             Some((nodes, ways))
         }
 
+        //weakly-connected adjacency: unions both directions of every edge, so a node reachable
+        //only via an incoming oneway edge still joins its neighbors' component in the flood fill
+        //below (the directed `edges` map alone would strand such nodes as singleton components)
+        fn undirected_adjacency(&self) -> HashMap<i64, HashSet<i64>> {
+            let mut adjacency: HashMap<i64, HashSet<i64>> = HashMap::new();
+            for (tail_id, neighbors) in &self.edges {
+                for head_id in neighbors.keys() {
+                    adjacency.entry(*tail_id).or_default().insert(*head_id);
+                    adjacency.entry(*head_id).or_default().insert(*tail_id);
+                }
+            }
+            adjacency
+        }
+
         pub fn reduce_to_largest_connected_component(self) -> Self {
-            //reduces graph to largest connected component through nodes visited with dijkstra
-            let mut counter = 0;
-            let mut number_times_node_visted: HashMap<i64, i32> = HashMap::new();
-            let mut shortest_path_graph = RoadDijkstra::new(&self);
-            let mut max_connections = 0;
-
-            while let Some(source_id) =
-                shortest_path_graph.get_unvisted_node_id(&number_times_node_visted)
-            {
-                counter += 1;
-                let mut shortest_path_graph = RoadDijkstra::new(&self);
-                shortest_path_graph.dijkstra(source_id, -1, &None, false);
-                for node in shortest_path_graph.visited_nodes.keys() {
-                    number_times_node_visted.insert(*node, counter);
+            //single-pass flood fill over an undirected view of the graph, tagging every node
+            //with a component id; replaces the old repeated-Dijkstra approach, which was both
+            //slower and, for directed graphs, not guaranteed to find the true largest component
+            let adjacency = self.undirected_adjacency();
+            let mut component_of: HashMap<i64, usize> = HashMap::new();
+            let mut component_sizes: HashMap<usize, usize> = HashMap::new();
+            let mut next_component_id = 0;
+
+            for &start_id in self.nodes.keys() {
+                if component_of.contains_key(&start_id) {
+                    continue;
                 }
-                if number_times_node_visted.len() > (self.nodes.len() / 2) {
-                    break;
+                let component_id = next_component_id;
+                next_component_id += 1;
+                let mut size = 0;
+                let mut frontier = vec![start_id];
+                while let Some(node_id) = frontier.pop() {
+                    if component_of.contains_key(&node_id) {
+                        continue;
+                    }
+                    component_of.insert(node_id, component_id);
+                    size += 1;
+                    if let Some(neighbors) = adjacency.get(&node_id) {
+                        frontier.extend(
+                            neighbors
+                                .iter()
+                                .filter(|head_id| !component_of.contains_key(head_id)),
+                        );
+                    }
                 }
+                component_sizes.insert(component_id, size);
             }
-            let mut new_node_list = Vec::new();
-            new_node_list = number_times_node_visted.iter().collect();
-            new_node_list.sort_by(|(node1, counter1), (node2, counter2)| counter1.cmp(counter2));
 
-            let connected_components = &mut new_node_list
-                .chunk_by(|(node1, counter1), (node2, counter2)| counter1 == counter2);
+            let largest_component_id = component_sizes
+                .iter()
+                .max_by_key(|(_, &size)| size)
+                .map(|(&component_id, _)| component_id);
+
+            let lcc_nodes = match largest_component_id {
+                Some(largest_component_id) => component_of
+                    .into_iter()
+                    .filter(|(_, component_id)| *component_id == largest_component_id)
+                    //ids reached only as the head of a oneway edge are not in self.nodes, since
+                    //::new drops nodes without outgoing edges; they are skipped here
+                    .filter_map(|(id, _)| self.nodes.get(&id).map(|node| (id, *node)))
+                    .collect::<HashMap<i64, Node>>(),
+                None => HashMap::new(),
+            };
+
+            RoadNetwork::new(lcc_nodes, self.raw_ways)
+        }
+
+        fn spatial_index(&self) -> &RTree<IndexedNode> {
+            //built once from the surviving, routable node set (only the LCC-reduced nodes are
+            //ever snapped to) and cached, since nearest_node/nearest_nodes/route would otherwise
+            //each pay a fresh O(n log n) bulk_load
+            self.spatial_index_cache.get_or_init(|| {
+                RTree::bulk_load(
+                    self.nodes
+                        .values()
+                        .map(|node| IndexedNode {
+                            id: node.id,
+                            lat: node.lat,
+                            lon: node.lon,
+                        })
+                        .collect(),
+                )
+            })
+        }
+
+        pub fn nearest_node(&self, lat: i64, lon: i64) -> Option<i64> {
+            //closest routable node to the given (lat, lon), scaled the same way as Node::lat/lon (degrees * 1e7)
+            self.spatial_index()
+                .nearest_neighbor(&[lat, lon])
+                .map(|node| node.id)
+        }
+
+        pub fn nearest_nodes(&self, lat: i64, lon: i64, k: usize) -> Vec<i64> {
+            //k closest routable nodes, nearest first; useful when the single nearest node has no usable edges
+            self.spatial_index()
+                .nearest_neighbor_iter(&[lat, lon])
+                .take(k)
+                .map(|node| node.id)
+                .collect()
+        }
+
+        pub fn route(&self, from: (f64, f64), to: (f64, f64)) -> Option<(Vec<i64>, u64)> {
+            //convenience wrapper: snaps both coordinates to the nearest routable node, then runs a*
+            let scale = |coord: f64| (coord * f64::powi(10.0, 7)) as i64;
+            let source_id = self.nearest_node(scale(from.0), scale(from.1))?;
+            let target_id = self.nearest_node(scale(to.0), scale(to.1))?;
+            RoadDijkstra::new(self).astar(source_id, target_id, &None)
+        }
+
+        fn bounding_box(&self) -> (i64, i64, i64, i64) {
+            //(min_lat, max_lat, min_lon, max_lon) over all surviving nodes
+            let mut min_lat = i64::MAX;
+            let mut max_lat = i64::MIN;
+            let mut min_lon = i64::MAX;
+            let mut max_lon = i64::MIN;
+            for node in self.nodes.values() {
+                min_lat = min_lat.min(node.lat);
+                max_lat = max_lat.max(node.lat);
+                min_lon = min_lon.min(node.lon);
+                max_lon = max_lon.max(node.lon);
+            }
+            (min_lat, max_lat, min_lon, max_lon)
+        }
 
-            let mut largest_node_set = Vec::new();
-            let mut prev_set_size = 0;
+        fn partition_into_grid(&mut self, cells_per_axis: usize) {
+            //simple rectangular grid over the lat/lon bounding box; cell id = row * cells_per_axis + col
+            let cells_per_axis = cells_per_axis.max(1);
+            let (min_lat, max_lat, min_lon, max_lon) = self.bounding_box();
+            let lat_span = (max_lat - min_lat).max(1) as i128;
+            let lon_span = (max_lon - min_lon).max(1) as i128;
+            self.partition = self
+                .nodes
+                .values()
+                .map(|node| {
+                    let row = (((node.lat - min_lat) as i128 * cells_per_axis as i128) / lat_span)
+                        .clamp(0, cells_per_axis as i128 - 1) as usize;
+                    let col = (((node.lon - min_lon) as i128 * cells_per_axis as i128) / lon_span)
+                        .clamp(0, cells_per_axis as i128 - 1) as usize;
+                    (node.id, row * cells_per_axis + col)
+                })
+                .collect();
+            self.num_cells = cells_per_axis * cells_per_axis;
+        }
 
-            for node_set in connected_components.by_ref() {
-                if node_set.len() > prev_set_size {
-                    largest_node_set = node_set.to_vec();
-                    prev_set_size = node_set.len();
+        //entry points into each cell: a node counts as a boundary node of its own cell if some
+        //edge crosses into it from a different cell. Must be based on incoming edges, not
+        //outgoing ones: with oneway edges a node can be a true entrance to a cell while having
+        //no edge of its own leaving the cell, and reverse-Dijkstra in compute_arc_flags needs
+        //every such entrance as a source or it silently under-flags legitimate routes in.
+        fn boundary_nodes_by_cell(&self) -> HashMap<usize, Vec<i64>> {
+            let mut boundaries: HashMap<usize, HashSet<i64>> = HashMap::new();
+            for (tail_id, neighbors) in &self.edges {
+                let Some(&tail_cell) = self.partition.get(tail_id) else {
+                    continue;
+                };
+                for head_id in neighbors.keys() {
+                    if let Some(&head_cell) = self.partition.get(head_id) {
+                        if head_cell != tail_cell {
+                            boundaries.entry(head_cell).or_default().insert(*head_id);
+                        }
+                    }
                 }
             }
+            boundaries
+                .into_iter()
+                .map(|(cell_id, nodes)| (cell_id, nodes.into_iter().collect()))
+                .collect()
+        }
 
-            let lcc_nodes = largest_node_set
-                .iter()
-                .map(|(id, _)| (**id, *self.nodes.get(id).unwrap()))
-                .collect::<HashMap<i64, Node>>();
+        fn reverse_edges(&self) -> HashMap<i64, HashMap<i64, u64>> {
+            let mut reverse: HashMap<i64, HashMap<i64, u64>> = HashMap::new();
+            for (tail_id, neighbors) in &self.edges {
+                for (head_id, (cost, _)) in neighbors {
+                    reverse.entry(*head_id).or_default().insert(*tail_id, *cost);
+                }
+            }
+            reverse
+        }
 
-            RoadNetwork::new(lcc_nodes, self.raw_ways)
+        pub fn compute_arc_flags(&mut self, cells_per_axis: usize) {
+            //arc-flags preprocessing: partition the graph into a grid, then for each cell run a
+            //reverse search from each of its boundary nodes and mark every edge on a resulting
+            //shortest path tree as relevant ("true") for routing into that cell
+            self.partition_into_grid(cells_per_axis);
+            for neighbors in self.edges.values_mut() {
+                for value in neighbors.values_mut() {
+                    value.1 = vec![false; self.num_cells];
+                }
+            }
+
+            let reverse = self.reverse_edges();
+            for (cell_id, sources) in self.boundary_nodes_by_cell() {
+                for source_id in sources {
+                    let dist_to_boundary = multi_source_dijkstra(&reverse, &[source_id]);
+                    for (tail_id, neighbors) in self.edges.iter_mut() {
+                        for (head_id, (cost, flags)) in neighbors.iter_mut() {
+                            if let (Some(&dist_tail), Some(&dist_head)) =
+                                (dist_to_boundary.get(tail_id), dist_to_boundary.get(head_id))
+                            {
+                                if dist_tail == dist_head + *cost {
+                                    flags[cell_id] = true;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        pub fn save(&self, cache_path: &str, source_pbf_path: &str) -> std::io::Result<()> {
+            //serializes the preprocessed graph alongside a hash of the source .pbf, so load() can
+            //detect whether the cache is still fresh without redoing preprocessing
+            let cached = CachedRoadNetwork {
+                source_hash: hash_file(source_pbf_path)?,
+                network: self.clone(),
+            };
+            let bytes =
+                bincode::serialize(&cached).expect("RoadNetwork should always be serializable");
+            std::fs::write(cache_path, bytes)
+        }
+
+        //None means the cache is stale (source .pbf changed since it was written) and must be rebuilt
+        pub fn load(cache_path: &str, source_pbf_path: &str) -> std::io::Result<Option<Self>> {
+            let bytes = std::fs::read(cache_path)?;
+            let cached: CachedRoadNetwork = bincode::deserialize(&bytes).map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("cache file is corrupt or from an incompatible version: {e}"),
+                )
+            })?;
+            let current_hash = hash_file(source_pbf_path)?;
+            Ok((cached.source_hash == current_hash).then_some(cached.network))
+        }
+
+        //chains a* legs across consecutive waypoints into one continuous path
+        fn route_in_order(&self, waypoints: &[i64]) -> Option<(Vec<i64>, u64)> {
+            let mut shortest_path_graph = RoadDijkstra::new(self);
+            let mut full_path: Vec<i64> = Vec::new();
+            let mut total_cost = 0;
+            for pair in waypoints.windows(2) {
+                let (leg_path, leg_cost) = shortest_path_graph.astar(pair[0], pair[1], &None)?;
+                total_cost += leg_cost;
+                if full_path.last() == Some(&pair[0]) {
+                    full_path.extend(leg_path.into_iter().skip(1));
+                } else {
+                    full_path.extend(leg_path);
+                }
+            }
+            Some((full_path, total_cost))
+        }
+
+        fn leg_cost(&self, from: i64, to: i64, leg_cache: &mut HashMap<(i64, i64), u64>) -> Option<u64> {
+            //cached a* cost for a single from -> to leg, shared across every permutation that uses it
+            if let Some(&cost) = leg_cache.get(&(from, to)) {
+                return Some(cost);
+            }
+            let (_, cost) = RoadDijkstra::new(self).astar(from, to, &None)?;
+            leg_cache.insert((from, to), cost);
+            Some(cost)
+        }
+
+        //intermediate stops above this count fall back to in-order routing instead of
+        //optimizing: permutations grow factorially, and 8! = 40320 is already the practical
+        //ceiling for searching every ordering per query
+        const MAX_OPTIMIZE_WAYPOINTS: usize = 8;
+
+        pub fn route_through(&self, waypoints: &[i64], optimize: bool) -> Option<(Vec<i64>, u64)> {
+            //chains shortest paths across an ordered list of waypoints; with optimize, instead
+            //searches every ordering of the intermediate stops (start and end stay fixed) and
+            //keeps the cheapest one, reusing leg costs across permutations via leg_cost's cache
+            if waypoints.len() < 2 {
+                return None;
+            }
+            let middle_len = waypoints.len().saturating_sub(2);
+            if !optimize || waypoints.len() <= 3 || middle_len > Self::MAX_OPTIMIZE_WAYPOINTS {
+                return self.route_in_order(waypoints);
+            }
+
+            let start = waypoints[0];
+            let end = waypoints[waypoints.len() - 1];
+            let mut middle = waypoints[1..waypoints.len() - 1].to_vec();
+            let mut orderings = Vec::new();
+            permutations(&mut middle, 0, &mut orderings);
+
+            let mut leg_cache: HashMap<(i64, i64), u64> = HashMap::new();
+            let best_order = orderings.into_iter().min_by_key(|order| {
+                let mut full_order = Vec::with_capacity(order.len() + 2);
+                full_order.push(start);
+                full_order.extend(order);
+                full_order.push(end);
+                full_order
+                    .windows(2)
+                    .map(|pair| {
+                        self.leg_cost(pair[0], pair[1], &mut leg_cache)
+                            .unwrap_or(u64::MAX)
+                    })
+                    .fold(0u64, u64::saturating_add)
+            })?;
+
+            let mut full_order = Vec::with_capacity(best_order.len() + 2);
+            full_order.push(start);
+            full_order.extend(best_order);
+            full_order.push(end);
+            self.route_in_order(&full_order)
+        }
+    }
+
+    fn permutations(items: &mut Vec<i64>, k: usize, out: &mut Vec<Vec<i64>>) {
+        //all orderings of items, generated in place by swapping (Heap-adjacent recursive scheme)
+        if k == items.len() {
+            out.push(items.clone());
+            return;
+        }
+        for i in k..items.len() {
+            items.swap(k, i);
+            permutations(items, k + 1, out);
+            items.swap(k, i);
+        }
+    }
+
+    fn multi_source_dijkstra(
+        graph: &HashMap<i64, HashMap<i64, u64>>,
+        sources: &[i64],
+    ) -> HashMap<i64, u64> {
+        //plain dijkstra seeded from several sources at once, used to build arc-flag shortest-path trees
+        let mut dist: HashMap<i64, u64> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        for &source_id in sources {
+            dist.insert(source_id, 0);
+            heap.push(Reverse((0u64, source_id)));
+        }
+        while let Some(Reverse((cost, node_id))) = heap.pop() {
+            if cost > *dist.get(&node_id).unwrap_or(&u64::MAX) {
+                continue;
+            }
+            let Some(neighbors) = graph.get(&node_id) else {
+                continue;
+            };
+            for (&next_id, &edge_cost) in neighbors {
+                let next_cost = cost + edge_cost;
+                if next_cost < *dist.get(&next_id).unwrap_or(&u64::MAX) {
+                    dist.insert(next_id, next_cost);
+                    heap.push(Reverse((next_cost, next_id)));
+                }
+            }
+        }
+        dist
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn node(id: i64, lat: i64, lon: i64) -> Node {
+            Node { id, lat, lon }
+        }
+
+        fn way(id: i64, refs: Vec<i64>, oneway: bool) -> Way {
+            Way {
+                id,
+                speed: 36, //10 m/s, keeps edge costs small integers
+                refs,
+                oneway,
+                maxspeed: None,
+            }
+        }
+
+        #[test]
+        fn undirected_adjacency_unions_both_directions_of_a_oneway_edge() {
+            //a node with an incoming oneway edge and no outgoing edge of its own must still show
+            //up as a neighbor of its predecessor, or the flood fill below would strand it
+            let mut edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> = HashMap::new();
+            edges.entry(1).or_default().insert(2, (1, Vec::new()));
+            let graph = RoadNetwork::for_testing(HashMap::new(), edges);
+
+            let adjacency = graph.undirected_adjacency();
+            assert_eq!(adjacency.get(&1).unwrap(), &HashSet::from([2]));
+            assert_eq!(adjacency.get(&2).unwrap(), &HashSet::from([1]));
+        }
+
+        #[test]
+        fn reduce_to_largest_connected_component_keeps_the_bigger_component() {
+            let nodes: HashMap<i64, Node> = [
+                node(1, 0, 0),
+                node(2, 0, 1_000_000),
+                node(3, 1_000_000, 0),
+                node(4, 1_000_000, 1_000_000),
+                node(5, 2_000_000, 2_000_000),
+            ]
+            .into_iter()
+            .map(|n| (n.id, n))
+            .collect();
+            let ways = vec![
+                way(1, vec![1, 2], false),       //2-node component
+                way(2, vec![3, 4, 5], false),    //3-node component, should win
+            ];
+
+            let reduced = RoadNetwork::new(nodes, ways).reduce_to_largest_connected_component();
+
+            let mut surviving: Vec<i64> = reduced.nodes.keys().copied().collect();
+            surviving.sort();
+            assert_eq!(surviving, vec![3, 4, 5]);
+        }
+
+        //A and A2 sit in the same grid cell with no other edge ever entering that cell, so their
+        //connecting edge never gets a "true" flag for it from compute_arc_flags's reverse search;
+        //B -> D is a oneway edge whose only purpose is to be D's entrance into its own cell, and D
+        //has no outgoing edge of its own
+        fn arc_flag_test_graph() -> RoadNetwork {
+            let nodes: HashMap<i64, Node> = [
+                node(1, 0, 0),          //A, cell (0,0)
+                node(2, 0, 10),         //A2, same cell as A
+                node(3, 100, 100),      //B, cell (1,1)
+                node(4, 100, 0),        //D, cell (1,0), only reachable via B -> D
+            ]
+            .into_iter()
+            .map(|n| (n.id, n))
+            .collect();
+            let mut edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> = HashMap::new();
+            edges.entry(1).or_default().insert(2, (5, Vec::new()));
+            edges.entry(2).or_default().insert(3, (3, Vec::new()));
+            edges.entry(3).or_default().insert(4, (7, Vec::new()));
+
+            let mut graph = RoadNetwork::for_testing(nodes, edges);
+            graph.compute_arc_flags(2);
+            graph
+        }
+
+        #[test]
+        fn arc_flags_bypass_allows_routing_inside_the_target_cell() {
+            let graph = arc_flag_test_graph();
+            let plain_cost = RoadDijkstra::new(&graph).dijkstra(1, 2, &None, false).map(|(_, cost)| cost);
+            let flagged_cost = RoadDijkstra::new(&graph).dijkstra(1, 2, &None, true).map(|(_, cost)| cost);
+            assert_eq!(flagged_cost, plain_cost);
+            assert_eq!(flagged_cost, Some(5));
+        }
+
+        #[test]
+        fn arc_flags_reach_an_entrance_node_with_no_outgoing_edges() {
+            let graph = arc_flag_test_graph();
+            let plain_cost = RoadDijkstra::new(&graph).dijkstra(1, 4, &None, false).map(|(_, cost)| cost);
+            let flagged_cost = RoadDijkstra::new(&graph).dijkstra(1, 4, &None, true).map(|(_, cost)| cost);
+            assert_eq!(flagged_cost, plain_cost);
+            assert_eq!(flagged_cost, Some(15));
+        }
+
+        #[test]
+        fn parse_maxspeed_handles_numeric_mph_and_none() {
+            assert_eq!(parse_maxspeed("50"), Some(50));
+            assert_eq!(parse_maxspeed(" 60 "), Some(60));
+            assert_eq!(parse_maxspeed("30 mph"), Some((30.0 * 1.60934) as u64));
+            assert_eq!(parse_maxspeed("none"), None);
+            assert_eq!(parse_maxspeed("RU:urban"), None);
+        }
+
+        #[test]
+        fn parse_maxspeed_rejects_non_positive_values() {
+            //a tagged maxspeed of 0 is erroneous data, not a real speed limit; treating it as a
+            //resolved speed would feed a zero divisor into RoadNetwork::new's cost calculation
+            assert_eq!(parse_maxspeed("0"), None);
+            assert_eq!(parse_maxspeed("0 mph"), None);
+        }
+
+        #[test]
+        fn parse_oneway_handles_forward_reverse_and_default() {
+            assert_eq!(parse_oneway("yes"), (true, false));
+            assert_eq!(parse_oneway("1"), (true, false));
+            assert_eq!(parse_oneway("-1"), (true, true));
+            assert_eq!(parse_oneway("no"), (false, false));
+        }
+
+        fn temp_path(name: &str) -> std::path::PathBuf {
+            std::env::temp_dir().join(format!("road_network_test_{name}_{}", std::process::id()))
+        }
+
+        fn small_network() -> RoadNetwork {
+            let nodes: HashMap<i64, Node> = [node(1, 0, 0), node(2, 0, 1_000_000)]
+                .into_iter()
+                .map(|n| (n.id, n))
+                .collect();
+            RoadNetwork::new(nodes, vec![way(1, vec![1, 2], false)])
+        }
+
+        #[test]
+        fn save_and_load_round_trips_when_source_is_unchanged() {
+            let source_path = temp_path("roundtrip_source");
+            let cache_path = temp_path("roundtrip_cache");
+            std::fs::write(&source_path, b"fake osm data").unwrap();
+
+            let network = small_network();
+            network
+                .save(cache_path.to_str().unwrap(), source_path.to_str().unwrap())
+                .unwrap();
+            let loaded =
+                RoadNetwork::load(cache_path.to_str().unwrap(), source_path.to_str().unwrap())
+                    .unwrap();
+
+            assert_eq!(loaded, Some(network));
+
+            std::fs::remove_file(&source_path).unwrap();
+            std::fs::remove_file(&cache_path).unwrap();
+        }
+
+        #[test]
+        fn load_returns_none_when_source_has_changed_since_save() {
+            let source_path = temp_path("stale_source");
+            let cache_path = temp_path("stale_cache");
+            std::fs::write(&source_path, b"original osm data").unwrap();
+
+            small_network()
+                .save(cache_path.to_str().unwrap(), source_path.to_str().unwrap())
+                .unwrap();
+            std::fs::write(&source_path, b"changed osm data").unwrap();
+
+            let loaded =
+                RoadNetwork::load(cache_path.to_str().unwrap(), source_path.to_str().unwrap())
+                    .unwrap();
+            assert_eq!(loaded, None);
+
+            std::fs::remove_file(&source_path).unwrap();
+            std::fs::remove_file(&cache_path).unwrap();
+        }
+
+        #[test]
+        fn load_returns_an_error_instead_of_panicking_on_a_corrupt_cache() {
+            let source_path = temp_path("corrupt_source");
+            let cache_path = temp_path("corrupt_cache");
+            std::fs::write(&source_path, b"osm data").unwrap();
+            std::fs::write(&cache_path, b"not a valid bincode payload").unwrap();
+
+            let result =
+                RoadNetwork::load(cache_path.to_str().unwrap(), source_path.to_str().unwrap());
+            assert!(result.is_err());
+            assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+            std::fs::remove_file(&source_path).unwrap();
+            std::fs::remove_file(&cache_path).unwrap();
+        }
+
+        fn bidirectional_edges(pairs: &[(i64, i64, u64)]) -> HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> {
+            let mut edges: HashMap<i64, HashMap<i64, (u64, Vec<bool>)>> = HashMap::new();
+            for &(a, b, cost) in pairs {
+                edges.entry(a).or_default().insert(b, (cost, Vec::new()));
+                edges.entry(b).or_default().insert(a, (cost, Vec::new()));
+            }
+            edges
+        }
+
+        #[test]
+        fn route_through_optimizes_the_order_of_intermediate_stops() {
+            //visiting 1, 2, 3, 4 costs 2 + 1 + 2 = 5 (each leg takes its own shortest path, e.g.
+            //1 -> 3 -> 2 instead of the 10-cost edge); visiting 1, 3, 2, 4 costs 1 + 1 + 1 = 3
+            let nodes: HashMap<i64, Node> = (1..=4).map(|id| (id, node(id, 0, id))).collect();
+            let edges = bidirectional_edges(&[(1, 2, 10), (1, 3, 1), (2, 3, 1), (2, 4, 1), (3, 4, 10)]);
+            let graph = RoadNetwork::for_testing(nodes, edges);
+
+            let (path, cost) = graph.route_through(&[1, 2, 3, 4], true).unwrap();
+            assert_eq!(cost, 3);
+            assert_eq!(path, vec![1, 3, 2, 4]);
+        }
+
+        #[test]
+        fn route_through_falls_back_to_in_order_above_the_optimize_cap() {
+            //9 intermediate stops is one more than MAX_OPTIMIZE_WAYPOINTS, so this must behave
+            //exactly like route_in_order instead of searching all 9! orderings
+            let waypoints: Vec<i64> = (1..=11).collect();
+            assert_eq!(waypoints.len() - 2, RoadNetwork::MAX_OPTIMIZE_WAYPOINTS + 1);
+
+            let nodes: HashMap<i64, Node> = waypoints.iter().map(|&id| (id, node(id, 0, id))).collect();
+            let chain_pairs: Vec<(i64, i64, u64)> =
+                waypoints.windows(2).map(|pair| (pair[0], pair[1], 1)).collect();
+            let edges = bidirectional_edges(&chain_pairs);
+            let graph = RoadNetwork::for_testing(nodes, edges);
+
+            let optimized = graph.route_through(&waypoints, true);
+            let in_order = graph.route_in_order(&waypoints);
+            assert_eq!(optimized, in_order);
+        }
+
+        #[test]
+        fn profile_speed_tables_enforce_distinct_access_rules() {
+            //motorway is car-only
+            assert_eq!(car_speed_calc("motorway"), Some(110));
+            assert_eq!(bicycle_speed_calc("motorway"), None);
+            assert_eq!(pedestrian_speed_calc("motorway"), None);
+
+            //footway is off-limits to cars but usable by bikes and pedestrians
+            assert_eq!(car_speed_calc("footway"), None);
+            assert_eq!(bicycle_speed_calc("footway"), Some(5));
+            assert_eq!(pedestrian_speed_calc("footway"), Some(5));
+
+            assert_eq!(Profile::Car.speed_calc("residential"), Some(30));
+            assert_eq!(Profile::Bicycle.speed_calc("residential"), Some(15));
+            assert_eq!(Profile::Pedestrian.speed_calc("residential"), Some(5));
         }
     }
 }
\ No newline at end of file